@@ -0,0 +1,81 @@
+//! Thread-block shared memory for device kernels
+//!
+//! A kernel requests shared memory through the [thread_block_shared!] macro rather than by
+//! constructing [ThreadBlockShared] directly, since each declaration needs its own `static`
+//! placed in the `.shared` link section -- the section the NVPTX backend lowers to PTX
+//! `.shared` state space (LLVM `addrspace(3)`) instead of ordinary per-thread storage.
+
+use core::ops::{Deref, DerefMut};
+
+/// A handle to a value living in per-block shared memory.
+///
+/// Every thread in the block sees the same backing storage. Obtain one with
+/// [thread_block_shared!], which declares the backing `static` in the `.shared` address space;
+/// do not construct this directly over an ordinary (stack/thread-local) value.
+#[repr(transparent)]
+pub struct ThreadBlockShared<T>(*mut T);
+
+unsafe impl<T> Sync for ThreadBlockShared<T> {}
+
+impl<T> ThreadBlockShared<T> {
+    /// Safety
+    /// ------
+    /// `ptr` must point at a `static` declared in the `.shared` link section, valid for the
+    /// lifetime of the kernel invocation. Use [thread_block_shared!] instead of calling this
+    /// directly.
+    #[doc(hidden)]
+    pub const unsafe fn from_raw(ptr: *mut T) -> Self {
+        ThreadBlockShared(ptr)
+    }
+}
+
+impl<T> Deref for ThreadBlockShared<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.0 }
+    }
+}
+
+impl<T> DerefMut for ThreadBlockShared<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.0 }
+    }
+}
+
+/// Declare a block of statically-sized shared memory and bind it to `$name`.
+///
+/// Lowers to a `static` in the `.shared` link section, so the NVPTX backend places it in PTX
+/// `.shared` state space rather than per-thread local storage. All threads in the block observe
+/// the same backing storage; synchronize (e.g. `accel_core::syncthreads()`) before reading what
+/// another thread wrote.
+#[macro_export]
+macro_rules! thread_block_shared {
+    ($name:ident : $ty:ty = $init:expr) => {
+        #[link_section = ".shared"]
+        static mut $name: $ty = $init;
+        #[allow(unused_unsafe)]
+        let $name = unsafe {
+            $crate::shared::ThreadBlockShared::from_raw(core::ptr::addr_of_mut!($name))
+        };
+    };
+}
+
+/// Get a pointer to the kernel's single dynamically-sized `extern __shared__` array, whose byte
+/// size was requested by the host through `LaunchConfig::shared_mem_bytes`.
+///
+/// Safety
+/// ------
+/// The caller must not index past `shared_mem_bytes / size_of::<T>()` elements, a bound only
+/// known at launch time, not to this function.
+pub unsafe fn dynamic_shared_mem<T>() -> *mut T {
+    // Declared, not defined: an external-linkage `.shared` symbol with no backing storage here
+    // is what the NVPTX backend lowers to PTX `.extern .shared`, mirroring CUDA C's `extern
+    // __shared__ T arr[]`. A *defined* `static mut ... = []` would instead
+    // introduce its own zero-sized allocation, which does not alias the dynamic region the
+    // driver sizes via `LaunchConfig::shared_mem_bytes`.
+    extern "C" {
+        #[link_section = ".shared"]
+        static mut DYNAMIC_SHARED_MEM: [u8; 0];
+    }
+    core::ptr::addr_of_mut!(DYNAMIC_SHARED_MEM) as *mut T
+}
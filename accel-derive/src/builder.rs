@@ -0,0 +1,45 @@
+//! Compile an annotated kernel function to PTX assembly by building it as a standalone
+//! `cdylib` crate for the `nvptx64-nvidia-cuda` target.
+
+use super::parser::Attributes;
+use failure::Fallible;
+use quote::quote;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use syn::ItemFn;
+
+/// Build `func` to PTX assembly and return its text, so the caller can embed it directly in the
+/// generated wrapper and load it with [Module::from_ptx](../../accel/struct.Module.html#method.from_ptx)
+/// -- no `.ptx` file is touched once macro expansion is done.
+pub fn build_ptx(func: &ItemFn, attrs: &Attributes) -> Fallible<String> {
+    let crate_dir = kernel_crate_dir(&func.sig.ident.to_string());
+    fs::create_dir_all(crate_dir.join("src"))?;
+    fs::write(crate_dir.join("Cargo.toml"), attrs.to_toml()?)?;
+    fs::write(crate_dir.join("src/lib.rs"), quote! { #func }.to_string())?;
+
+    let status = Command::new("cargo")
+        .current_dir(&crate_dir)
+        .args(&[
+            "rustc",
+            "--release",
+            "--target",
+            "nvptx64-nvidia-cuda",
+            "--",
+            "--emit=asm",
+        ])
+        .status()?;
+    if !status.success() {
+        failure::bail!("Failed to compile kernel `{}` to PTX", func.sig.ident);
+    }
+
+    let ptx_path = crate_dir
+        .join("target/nvptx64-nvidia-cuda/release/deps")
+        .join(format!("{}.s", func.sig.ident));
+    Ok(fs::read_to_string(ptx_path)?)
+}
+
+/// Scratch directory the kernel is built in, one per kernel function name.
+fn kernel_crate_dir(kernel_name: &str) -> PathBuf {
+    std::env::temp_dir().join("accel-derive").join(kernel_name)
+}
@@ -0,0 +1,87 @@
+//! `#[kernel]` procedural macro: compiles the annotated function into a CUDA kernel and replaces
+//! it with a host-side wrapper of the same name.
+
+extern crate proc_macro;
+
+mod builder;
+mod parser;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, punctuated::Punctuated, token::Comma, FnArg, Ident, ItemFn, Type};
+
+/// Compile a `fn` to a CUDA kernel and replace it with a host-side launcher of the same name.
+///
+/// ```ignore
+/// #[kernel]
+/// pub unsafe fn add(a: *const f32, b: *const f32, c: *mut f32) { .. }
+/// ```
+///
+/// expands to a pair of host functions, `add` and `add_async`, that take a `&Context`/
+/// `&LaunchConfig` (the `_async` variant additionally takes a `&Stream`) and a reference to a
+/// tuple holding a reference to each kernel argument, e.g.
+/// `add(&ctx, &config, &(&a_ptr, &b_ptr, &c_ptr))`.
+///
+/// The compiled PTX is embedded as a `&'static str` in the generated wrapper and loaded with
+/// [Module::from_ptx](../accel/struct.Module.html#method.from_ptx) at launch time, so no `.ptx`
+/// file is ever touched once this macro has expanded.
+#[proc_macro_attribute]
+pub fn kernel(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attrs = parse_macro_input!(attr with syn::Attribute::parse_outer);
+    let func = parse_macro_input!(item as ItemFn);
+
+    let kernel_attrs = parser::parse_attrs(&attrs).expect("Invalid #[kernel] attribute");
+    let ptx = builder::build_ptx(&func, &kernel_attrs).expect("Failed to compile kernel to PTX");
+
+    let name = &func.sig.ident;
+    let async_name = Ident::new(&format!("{}_async", name), Span::call_site());
+    let vis = &func.vis;
+    let fn_name = name.to_string();
+    let arg_types = kernel_arg_types(&func.sig.inputs);
+    let n_args = arg_types.len();
+    let indices: Vec<syn::Index> = (0..n_args).map(syn::Index::from).collect();
+
+    let expanded = quote! {
+        #vis fn #name(
+            ctx: &::accel::Context,
+            config: &::accel::LaunchConfig,
+            args: &(#(&#arg_types,)*),
+        ) -> ::accel::error::Result<()> {
+            const PTX: &str = #ptx;
+            let module = ::accel::Module::from_ptx(ctx, PTX, &[])?;
+            let mut function = module.get_function(#fn_name)?;
+            let mut raw_args: [*mut ::std::os::raw::c_void; #n_args] = [
+                #( args.#indices as *const _ as *mut ::std::os::raw::c_void, )*
+            ];
+            unsafe { function.launch(raw_args.as_mut_ptr(), config) }
+        }
+
+        #vis fn #async_name(
+            ctx: &::accel::Context,
+            stream: &::accel::Stream,
+            config: &::accel::LaunchConfig,
+            args: &(#(&#arg_types,)*),
+        ) -> ::accel::error::Result<()> {
+            const PTX: &str = #ptx;
+            let module = ::accel::Module::from_ptx(ctx, PTX, &[])?;
+            let mut function = module.get_function(#fn_name)?;
+            let mut raw_args: [*mut ::std::os::raw::c_void; #n_args] = [
+                #( args.#indices as *const _ as *mut ::std::os::raw::c_void, )*
+            ];
+            unsafe { function.launch_async(stream, raw_args.as_mut_ptr(), config) }
+        }
+    };
+    expanded.into()
+}
+
+/// Collect the parameter types of the annotated kernel function, in declaration order.
+fn kernel_arg_types(inputs: &Punctuated<FnArg, Comma>) -> Vec<Type> {
+    inputs
+        .iter()
+        .map(|arg| match arg {
+            FnArg::Typed(pat_type) => (*pat_type.ty).clone(),
+            FnArg::Receiver(_) => panic!("#[kernel] functions cannot take `self`"),
+        })
+        .collect()
+}
@@ -21,6 +21,13 @@ impl Default for Attributes {
     }
 }
 
+impl Attributes {
+    /// Render as the `Cargo.toml` of the standalone crate the kernel is built in.
+    pub fn to_toml(&self) -> Fallible<String> {
+        Ok(toml::to_string(self)?)
+    }
+}
+
 pub fn parse_attrs(attrs: &[syn::Attribute]) -> Fallible<Attributes> {
     let mut kernel_attrs = Attributes::default();
     for attr in attrs {
@@ -17,6 +17,7 @@ fn main() -> Result<()> {
     a[1] = 1;
     a[2] = 2;
     a[3] = 3;
-    read_host_memory(&ctx, 1, 4, &(&a.as_ptr(),))?;
+    let config = LaunchConfig::new(Grid::x(1), Block::x(4));
+    read_host_memory(&ctx, &config, &(&a.as_ptr(),))?;
     Ok(())
 }
\ No newline at end of file
@@ -5,7 +5,10 @@
 
 use crate::{error::*, *};
 use cuda::*;
-use std::sync::{Arc, Once};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once, Weak};
 
 /// Handler for device and its primary context
 #[derive(Debug, PartialEq, PartialOrd)]
@@ -74,8 +77,48 @@ impl Device {
     pub fn create_context(&self) -> Arc<Context> {
         Arc::new(Context::create(self.device))
     }
+
+    /// Get (or create) the primary context of this device.
+    ///
+    /// Unlike [Device::create_context], which always makes a fresh floating context via
+    /// `cuCtxCreate_v2`, this retains the device's *primary* context, which is what the CUDA
+    /// runtime API and interop libraries (cuDNN, Blender's Cycles backend, etc.) implicitly
+    /// expect to be current. Repeated calls on the same device return the same underlying
+    /// context rather than creating a new one.
+    ///
+    /// See also [cuDevicePrimaryCtxRetain].
+    ///
+    /// [cuDevicePrimaryCtxRetain]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__PRIMARY__CTX.html#group__CUDA__PRIMARY__CTX_1g9051f2d5c31501997a6cb0530290a300
+    pub fn primary_context(&self) -> Arc<Context> {
+        let mut contexts = PRIMARY_CONTEXTS
+            .lock()
+            .expect("Primary context registry has been poisoned");
+        if let Some(ctx) = contexts.get(&self.device).and_then(Weak::upgrade) {
+            return ctx;
+        }
+        let ctx = Arc::new(Context::primary(self.device));
+        contexts.insert(self.device, Arc::downgrade(&ctx));
+        ctx
+    }
+
+    /// Set the flags used when this device's primary context is created.
+    ///
+    /// This must be called before the primary context has been retained, i.e. before the first
+    /// call to [Device::primary_context]; otherwise the driver rejects the request.
+    ///
+    /// See also [cuDevicePrimaryCtxSetFlags].
+    ///
+    /// [cuDevicePrimaryCtxSetFlags]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__PRIMARY__CTX.html
+    pub fn set_primary_context_flags(&self, flags: CUctx_flags) -> Result<()> {
+        unsafe { ffi_call!(cuDevicePrimaryCtxSetFlags, self.device, flags as u32) }
+    }
 }
 
+/// Registry of retained primary contexts, keyed by device, so that repeated calls to
+/// [Device::primary_context] return the same [Context] rather than retaining it twice.
+static PRIMARY_CONTEXTS: Lazy<Mutex<HashMap<CUdevice, Weak<Context>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
 /// RAII handler for using CUDA context
 ///
 /// As described in [CUDA Programming Guide], library using CUDA should push context before using
@@ -89,14 +132,16 @@ pub struct ContextGuard {
 impl ContextGuard {
     /// Make context as current on this thread
     pub fn guard_context(ctx: Arc<Context>) -> Self {
-        ctx.push();
+        ctx.push().expect("Failed to push an owned context; it cannot have expired");
         Self { ctx }
     }
 }
 
 impl Drop for ContextGuard {
     fn drop(&mut self) {
-        self.ctx.pop();
+        if let Err(e) = self.ctx.pop() {
+            log::error!("Failed to pop context: {:?}", e);
+        }
     }
 }
 
@@ -121,15 +166,36 @@ pub trait Contexted {
     }
 }
 
+/// Distinguishes a floating context created by this crate from a device's primary context,
+/// which is merely retained, so that [Drop for Context](Context#impl-Drop-for-Context) can
+/// release each the way it was acquired.
+#[derive(Debug, PartialEq)]
+enum ContextKind {
+    /// Created with `cuCtxCreate_v2`; destroyed with `cuCtxDestroy_v2`.
+    Owned,
+    /// Retained with `cuDevicePrimaryCtxRetain`; released (not destroyed) with
+    /// `cuDevicePrimaryCtxRelease`.
+    Primary(CUdevice),
+}
+
 /// Owend handler for CUDA context
 #[derive(Debug, PartialEq)]
 pub struct Context {
     ptr: CUcontext,
+    kind: ContextKind,
+    token: u64,
 }
 
 impl Drop for Context {
     fn drop(&mut self) {
-        if let Err(e) = unsafe { ffi_call!(cuCtxDestroy_v2, self.ptr) } {
+        unregister_context(self.ptr);
+        let result = match self.kind {
+            ContextKind::Owned => unsafe { ffi_call!(cuCtxDestroy_v2, self.ptr) },
+            ContextKind::Primary(device) => unsafe {
+                ffi_call!(cuDevicePrimaryCtxRelease, device)
+            },
+        };
+        if let Err(e) = result {
             log::error!("Context remove failed: {:?}", e);
         }
     }
@@ -149,6 +215,7 @@ unsafe impl Sync for Context {}
 #[derive(Debug, PartialEq)]
 pub struct ContextRef {
     ptr: CUcontext,
+    token: u64,
 }
 
 impl std::cmp::PartialEq<ContextRef> for Context {
@@ -166,55 +233,97 @@ impl std::cmp::PartialEq<Context> for ContextRef {
 unsafe impl Send for ContextRef {}
 unsafe impl Sync for ContextRef {}
 
+/// Liveness registry for every live [Context], keyed by its `CUcontext` pointer and a
+/// monotonically increasing generation token.
+///
+/// A [ContextRef] captures `(ptr, token)` when created; [Context::drop] removes the entry for
+/// `ptr` when the owning context goes away. Because `CUcontext` pointers can be reused by the
+/// driver once freed, a stale `ContextRef` must be checked against the *token*, not just the
+/// pointer, to detect that its owner is gone -- this is what makes `ContextRef::get_ptr` safe
+/// rather than merely documented as unsafe.
+static CONTEXT_LIVENESS: Lazy<Mutex<HashMap<CUcontext, u64>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Register a newly created/retained context, returning its generation token.
+fn register_context(ptr: CUcontext) -> u64 {
+    let token = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    CONTEXT_LIVENESS
+        .lock()
+        .expect("Context liveness registry has been poisoned")
+        .insert(ptr, token);
+    token
+}
+
+/// Remove a context's entry when it is dropped.
+fn unregister_context(ptr: CUcontext) {
+    CONTEXT_LIVENESS
+        .lock()
+        .expect("Context liveness registry has been poisoned")
+        .remove(&ptr);
+}
+
+/// Check whether `(ptr, token)` still names a live context.
+fn context_is_live(ptr: CUcontext, token: u64) -> bool {
+    CONTEXT_LIVENESS
+        .lock()
+        .expect("Context liveness registry has been poisoned")
+        .get(&ptr)
+        == Some(&token)
+}
+
 /// Common implementations for Context, ContextRef
 pub(crate) trait ContextImpl {
-    fn get_ptr(&self) -> CUcontext;
+    /// Get the raw context pointer, returning `AccelError::ContextExpired` if the owning
+    /// [Context] has since been dropped.
+    fn get_ptr(&self) -> Result<CUcontext>;
 
     /// Push to the context stack of this thread
-    fn push(&self) {
-        unsafe {
-            ffi_call!(cuCtxPushCurrent_v2, self.get_ptr()).expect("Failed to push context");
-        }
+    fn push(&self) -> Result<()> {
+        unsafe { ffi_call!(cuCtxPushCurrent_v2, self.get_ptr()?) }
     }
 
     /// Pop from the context stack of this thread
-    fn pop(&self) {
-        let ptr = unsafe { ffi_new!(cuCtxPopCurrent_v2).expect("Failed to pop current context") };
+    fn pop(&self) -> Result<()> {
+        let ptr = unsafe { ffi_new!(cuCtxPopCurrent_v2)? };
         if ptr.is_null() {
             panic!("No current context");
         }
-        assert!(ptr == self.get_ptr(), "Pop must return same pointer");
+        assert!(ptr == self.get_ptr()?, "Pop must return same pointer");
+        Ok(())
     }
 
     /// Get API version
-    fn version(&self) -> u32 {
+    fn version(&self) -> Result<u32> {
         let mut version: u32 = 0;
-        unsafe { ffi_call!(cuCtxGetApiVersion, self.get_ptr(), &mut version as *mut _) }
-            .expect("Failed to get Driver API version");
-        version
+        unsafe { ffi_call!(cuCtxGetApiVersion, self.get_ptr()?, &mut version as *mut _) }?;
+        Ok(version)
     }
 
     /// Block until all tasks in this context to be complete.
     fn sync(&self) -> Result<()> {
-        self.push();
+        self.push()?;
         unsafe {
             ffi_call!(cuCtxSynchronize)?;
         }
-        self.pop();
+        self.pop()?;
         Ok(())
     }
 }
 
 impl ContextImpl for Context {
-    fn get_ptr(&self) -> CUcontext {
-        self.ptr
+    fn get_ptr(&self) -> Result<CUcontext> {
+        Ok(self.ptr)
     }
 }
 
 impl ContextImpl for ContextRef {
-    fn get_ptr(&self) -> CUcontext {
-        // FIXME Check pointer is still valid
-        self.ptr
+    fn get_ptr(&self) -> Result<CUcontext> {
+        if context_is_live(self.ptr, self.token) {
+            Ok(self.ptr)
+        } else {
+            Err(AccelError::ContextExpired)
+        }
     }
 }
 
@@ -232,18 +341,46 @@ impl Context {
         if ptr.is_null() {
             panic!("Cannot crate a new context");
         }
-        let ctx = Context { ptr };
-        ctx.pop();
+        let token = register_context(ptr);
+        let ctx = Context {
+            ptr,
+            kind: ContextKind::Owned,
+            token,
+        };
+        ctx.pop().expect("Failed to pop a context that was just pushed");
         ctx
     }
 
+    /// Retain the primary context of `device`, creating it if this is the first retain.
+    ///
+    /// See also [cuDevicePrimaryCtxRetain].
+    ///
+    /// [cuDevicePrimaryCtxRetain]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__PRIMARY__CTX.html#group__CUDA__PRIMARY__CTX_1g9051f2d5c31501997a6cb0530290a300
+    fn primary(device: CUdevice) -> Self {
+        let ptr = unsafe { ffi_new!(cuDevicePrimaryCtxRetain, device) }
+            .expect("Failed to retain the primary context");
+        if ptr.is_null() {
+            panic!("Cannot retain the primary context");
+        }
+        let token = register_context(ptr);
+        Context {
+            ptr,
+            kind: ContextKind::Primary(device),
+            token,
+        }
+    }
+
     /// Get a reference
     ///
     /// This is **NOT** a Rust reference, i.e. you can drop owned context while the reference exists.
-    /// The reference becomes expired after owned context is released, and it will cause a runtime error.
+    /// The reference is checked against the liveness registry on every use, so using it after the
+    /// owning context has been dropped returns `AccelError::ContextExpired` instead of risking UB.
     ///
     pub fn get_ref(&self) -> ContextRef {
-        ContextRef { ptr: self.ptr }
+        ContextRef {
+            ptr: self.ptr,
+            token: self.token,
+        }
     }
 }
 
@@ -283,13 +420,13 @@ mod tests {
         Ok(())
     }
 
-    #[should_panic]
     #[test]
     fn expired_context_ref() {
         let device = Device::nth(0).unwrap();
         let ctx = device.create_context();
         let ctx_ref = ctx.get_ref();
         drop(ctx);
-        let _version = ctx_ref.version(); // ctx has been expired
+        // ctx has been dropped: the reference is detected as expired rather than dereferenced
+        assert!(matches!(ctx_ref.version(), Err(AccelError::ContextExpired)));
     }
 }
@@ -0,0 +1,27 @@
+//! Error and Result for this crate
+
+use failure::Fail;
+
+#[derive(Debug, Fail)]
+pub enum AccelError {
+    #[fail(
+        display = "Device({}) not found; only {} device(s) are available",
+        id, count
+    )]
+    DeviceNotFound { id: usize, count: usize },
+
+    #[fail(display = "Context has already been dropped")]
+    ContextExpired,
+
+    #[fail(display = "Module JIT failed:\n{}", log)]
+    JitLoadFailed { log: String },
+
+    #[fail(
+        display = "Kernel requests {} bytes of shared memory (static + dynamic), \
+                    more than this device's {} byte per-block opt-in limit",
+        requested, max
+    )]
+    SharedMemoryLimitExceeded { requested: u32, max: u32 },
+}
+
+pub type Result<T> = std::result::Result<T, AccelError>;
@@ -0,0 +1,185 @@
+//! Device memory handler and host↔device copies
+
+use super::CudaMemory;
+use crate::{device::*, error::*, ffi_call, ffi_new, stream::Stream};
+use cuda::*;
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+/// Memory allocated on the device, tied to the [Context] it was allocated in
+pub struct DeviceMemory<'ctx, T> {
+    ptr: CUdeviceptr,
+    size: usize,
+    context: &'ctx Context,
+    phantom: PhantomData<T>,
+}
+
+impl<'ctx, T> Drop for DeviceMemory<'ctx, T> {
+    fn drop(&mut self) {
+        let _g = self.guard_context();
+        if let Err(e) = ffi_call!(cuMemFree_v2, self.ptr) {
+            log::error!("Cannot free device memory: {:?}", e);
+        }
+    }
+}
+
+impl<'ctx, T> Contexted for DeviceMemory<'ctx, T> {
+    fn get_context(&self) -> &Context {
+        &self.context
+    }
+}
+
+impl<'ctx, T> CudaMemory<T> for DeviceMemory<'ctx, T> {
+    fn as_ptr(&self) -> *const T {
+        self.ptr as *const T
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr as *mut T
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+}
+
+impl<'ctx, T: Copy> DeviceMemory<'ctx, T> {
+    /// Allocate `len` elements of device memory, leaving its contents unspecified.
+    ///
+    /// Panic
+    /// ------
+    /// - when memory allocation failed, including the `len == 0` case
+    fn alloc(context: &'ctx Context, len: usize) -> Self {
+        assert!(len > 0, "Zero-sized malloc is forbidden");
+        let _g = context.guard_context();
+        let bytes = len * size_of::<T>();
+        let ptr = ffi_new!(cuMemAlloc_v2, bytes).expect("Cannot allocate device memory");
+        DeviceMemory {
+            ptr,
+            size: len,
+            context,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Allocate `len` elements of device memory, zero-initialized.
+    ///
+    /// Panic
+    /// ------
+    /// - when memory allocation failed, including the `len == 0` case
+    pub fn alloc_zeros(context: &'ctx Context, len: usize) -> Self {
+        let mem = Self::alloc(context, len);
+        let _g = mem.guard_context();
+        ffi_call!(cuMemsetD8_v2, mem.ptr, 0, mem.size * size_of::<T>())
+            .expect("Cannot zero-fill device memory");
+        mem
+    }
+
+    /// Allocate device memory and copy `src` into it.
+    ///
+    /// `src` covers the whole allocation, so this skips the zero-fill [alloc_zeros](Self::alloc_zeros)
+    /// would otherwise do before immediately overwriting it.
+    pub fn from_slice(context: &'ctx Context, src: &[T]) -> Self {
+        let mut dest = Self::alloc(context, src.len());
+        dest.copy_from(src).expect("Cannot copy host slice to device");
+        dest
+    }
+
+    /// Blocking copy from a host slice into this device memory.
+    ///
+    /// See also [cuMemcpyHtoD_v2].
+    ///
+    /// [cuMemcpyHtoD_v2]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__MEM.html#group__CUDA__MEM_1g4d32266788c440b0220b1a9ba5795169
+    pub fn copy_from(&mut self, src: &[T]) -> Result<()> {
+        assert_eq!(src.len(), self.size, "Source length does not match");
+        let _g = self.guard_context();
+        ffi_call!(
+            cuMemcpyHtoD_v2,
+            self.ptr,
+            src.as_ptr() as *const _,
+            src.len() * size_of::<T>()
+        )
+    }
+
+    /// Blocking copy from this device memory into a host slice.
+    ///
+    /// See also [cuMemcpyDtoH_v2].
+    ///
+    /// [cuMemcpyDtoH_v2]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__MEM.html#group__CUDA__MEM_1g3480368ee0208a98f75019c9a8450893
+    pub fn copy_to_host(&self, dest: &mut [T]) -> Result<()> {
+        assert_eq!(dest.len(), self.size, "Destination length does not match");
+        let _g = self.guard_context();
+        ffi_call!(
+            cuMemcpyDtoH_v2,
+            dest.as_mut_ptr() as *mut _,
+            self.ptr,
+            dest.len() * size_of::<T>()
+        )
+    }
+
+    /// Queue a host-to-device copy onto `stream` without blocking the host thread.
+    ///
+    /// `src` must stay alive and unmoved until the copy is known to have completed, e.g. by
+    /// calling [Stream::synchronize].
+    ///
+    /// See also [cuMemcpyHtoDAsync_v2].
+    ///
+    /// [cuMemcpyHtoDAsync_v2]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__MEM.html#group__CUDA__MEM_1g56f30236c7c5247f8e061b59d3268362
+    pub fn copy_from_async(&mut self, stream: &Stream, src: &[T]) -> Result<()> {
+        assert_eq!(src.len(), self.size, "Source length does not match");
+        let _g = self.guard_context();
+        ffi_call!(
+            cuMemcpyHtoDAsync_v2,
+            self.ptr,
+            src.as_ptr() as *const _,
+            src.len() * size_of::<T>(),
+            stream.as_ptr()
+        )
+    }
+
+    /// Queue a device-to-host copy onto `stream` without blocking the host thread.
+    ///
+    /// `dest` must stay alive and unmoved until the copy is known to have completed, e.g. by
+    /// calling [Stream::synchronize].
+    ///
+    /// See also [cuMemcpyDtoHAsync_v2].
+    ///
+    /// [cuMemcpyDtoHAsync_v2]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__MEM.html#group__CUDA__MEM_1g7f0f98c444d29623a037ee64dfbf9dd3
+    pub fn copy_to_host_async(&self, stream: &Stream, dest: &mut [T]) -> Result<()> {
+        assert_eq!(dest.len(), self.size, "Destination length does not match");
+        let _g = self.guard_context();
+        ffi_call!(
+            cuMemcpyDtoHAsync_v2,
+            dest.as_mut_ptr() as *mut _,
+            self.ptr,
+            dest.len() * size_of::<T>(),
+            stream.as_ptr()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::Device;
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let device = Device::nth(0)?;
+        let ctx = device.create_context();
+        let input: Vec<i32> = (0..12).collect();
+        let mut mem = DeviceMemory::from_slice(&ctx, &input);
+        let mut output = vec![0i32; 12];
+        mem.copy_to_host(&mut output)?;
+        assert_eq!(input, output);
+        Ok(())
+    }
+
+    #[should_panic(expected = "Zero-sized malloc is forbidden")]
+    #[test]
+    fn alloc_zeros_zero_len() {
+        let device = Device::nth(0).unwrap();
+        let ctx = device.create_context();
+        let _a = DeviceMemory::<i32>::alloc_zeros(&ctx, 0);
+    }
+}
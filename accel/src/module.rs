@@ -0,0 +1,378 @@
+//! CUDA [Module] and [Function]
+//!
+//! [Module]:   https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__MODULE.html
+//! [Function]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__EXEC.html
+
+use crate::{device::*, error::*, ffi_call, ffi_new, stream::Stream};
+use cuda::*;
+use std::ffi::CString;
+use std::os::raw::c_void;
+use std::ptr::null_mut;
+
+/// Owned handler for CUDA module, tied to the [Context] it was loaded into
+pub struct Module<'ctx> {
+    ptr: CUmodule,
+    context: &'ctx Context,
+}
+
+impl<'ctx> Drop for Module<'ctx> {
+    fn drop(&mut self) {
+        if let Err(e) = ffi_call!(cuModuleUnload, self.ptr) {
+            log::error!("Failed to unload module: {:?}", e);
+        }
+    }
+}
+
+impl<'ctx> Contexted for Module<'ctx> {
+    fn get_context(&self) -> &Context {
+        &self.context
+    }
+}
+
+impl<'ctx> Module<'ctx> {
+    /// Load a module from a `.ptx`/`.cubin` file on disk.
+    ///
+    /// See also [cuModuleLoad].
+    ///
+    /// [cuModuleLoad]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__MODULE.html#group__CUDA__MODULE_1g366093bd269dafd0af21f1c7d18115d3
+    pub fn load(context: &'ctx Context, filename: &str) -> Result<Self> {
+        let _g = context.guard_context();
+        let filename = CString::new(filename).expect("Failed to convert to CString");
+        let ptr = unsafe { ffi_new!(cuModuleLoad, filename.as_ptr())? };
+        Ok(Module { ptr, context })
+    }
+
+    /// Get a kernel function defined in this module by name.
+    pub fn get_function<'m>(&'m self, name: &str) -> Result<Function<'m, 'ctx>> {
+        let name = CString::new(name).expect("Failed to convert to CString");
+        let func = unsafe { ffi_new!(cuModuleGetFunction, self.ptr, name.as_ptr())? };
+        Ok(Function { func, module: self })
+    }
+
+    /// Load a module from PTX assembly held in memory, without ever touching a temp file.
+    ///
+    /// This is what the `#[kernel]` codegen uses: the compiled PTX is embedded as a `&'static
+    /// str` in the generated wrapper and loaded here at launch time.
+    pub fn from_ptx(context: &'ctx Context, ptx: &str, opts: &[JitOption]) -> Result<Self> {
+        let ptx = CString::new(ptx).expect("PTX source contains an interior NUL byte");
+        Self::from_data(context, ptx.as_bytes_with_nul(), opts)
+    }
+
+    /// Load a module from an in-memory PTX or cubin image.
+    ///
+    /// See also [cuModuleLoadDataEx]. On failure, the `ptxas`/JIT linker log is returned to the
+    /// caller as `AccelError::JitLoadFailed` rather than only being logged, so it is visible even
+    /// with logging turned off.
+    ///
+    /// [cuModuleLoadDataEx]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__MODULE.html#group__CUDA__MODULE_1g9e8047e9ff8d0f0b61c962b3eda529b0
+    pub fn from_data(context: &'ctx Context, image: &[u8], opts: &[JitOption]) -> Result<Self> {
+        let _g = context.guard_context();
+        const LOG_BUFFER_SIZE: usize = 8192;
+        let mut info_log = vec![0u8; LOG_BUFFER_SIZE];
+        let mut error_log = vec![0u8; LOG_BUFFER_SIZE];
+
+        let (mut keys, mut vals) = jit_option_arrays(opts);
+        keys.push(CUjit_option_enum::CU_JIT_INFO_LOG_BUFFER);
+        vals.push(info_log.as_mut_ptr() as *mut c_void);
+        keys.push(CUjit_option_enum::CU_JIT_INFO_LOG_BUFFER_SIZE_BYTES);
+        vals.push(LOG_BUFFER_SIZE as *mut c_void);
+        keys.push(CUjit_option_enum::CU_JIT_ERROR_LOG_BUFFER);
+        vals.push(error_log.as_mut_ptr() as *mut c_void);
+        keys.push(CUjit_option_enum::CU_JIT_ERROR_LOG_BUFFER_SIZE_BYTES);
+        vals.push(LOG_BUFFER_SIZE as *mut c_void);
+
+        let result = unsafe {
+            ffi_new!(
+                cuModuleLoadDataEx,
+                image.as_ptr() as *const c_void,
+                keys.len() as u32,
+                keys.as_mut_ptr(),
+                vals.as_mut_ptr()
+            )
+        };
+        match result {
+            Ok(ptr) => Ok(Module { ptr, context }),
+            Err(e) => {
+                let log = format!(
+                    "driver error: {:?}\n--- info log ---\n{}\n--- error log ---\n{}",
+                    e,
+                    String::from_utf8_lossy(trim_nul(&info_log)),
+                    String::from_utf8_lossy(trim_nul(&error_log))
+                );
+                log::error!("Module JIT failed: {}", log);
+                Err(AccelError::JitLoadFailed { log })
+            }
+        }
+    }
+}
+
+/// Trim the unused, zero-filled tail CUDA leaves in a fixed-size JIT log buffer.
+fn trim_nul(buf: &[u8]) -> &[u8] {
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    &buf[..len]
+}
+
+/// A single `CU_JIT_*` option for [Module::from_ptx]/[Module::from_data]
+#[derive(Debug, Clone, Copy)]
+pub enum JitOption {
+    /// `CU_JIT_MAX_REGISTERS`: maximum number of registers a thread may use
+    MaxRegisters(u32),
+    /// `CU_JIT_OPTIMIZATION_LEVEL`: 0 (none) through 4 (highest, the default)
+    OptimizationLevel(u32),
+    /// `CU_JIT_TARGET`: the virtual architecture to JIT for
+    Target(CUjit_target),
+}
+
+fn jit_option_arrays(opts: &[JitOption]) -> (Vec<CUjit_option>, Vec<*mut c_void>) {
+    let mut keys = Vec::new();
+    let mut vals = Vec::new();
+    for opt in opts {
+        let (key, val) = match *opt {
+            JitOption::MaxRegisters(n) => (CUjit_option_enum::CU_JIT_MAX_REGISTERS, n as usize),
+            JitOption::OptimizationLevel(n) => {
+                (CUjit_option_enum::CU_JIT_OPTIMIZATION_LEVEL, n as usize)
+            }
+            JitOption::Target(t) => (CUjit_option_enum::CU_JIT_TARGET, t as usize),
+        };
+        keys.push(key);
+        vals.push(val as *mut c_void);
+    }
+    (keys, vals)
+}
+
+/// Non-owned handler for a `CUfunction` defined in a [Module]
+pub struct Function<'m, 'ctx> {
+    func: CUfunction,
+    module: &'m Module<'ctx>,
+}
+
+impl<'m, 'ctx> Contexted for Function<'m, 'ctx> {
+    fn get_context(&self) -> &Context {
+        self.module.get_context()
+    }
+}
+
+impl<'m, 'ctx> Function<'m, 'ctx> {
+    /// Launch this kernel on the default stream, blocking until it is enqueued.
+    ///
+    /// This is a shorthand for [launch_async](Function::launch_async) that does not allow
+    /// overlapping with other work; prefer `launch_async` with an explicit [Stream] when
+    /// submitting more than one kernel.
+    pub unsafe fn launch(&mut self, args: *mut *mut c_void, config: &LaunchConfig) -> Result<()> {
+        self.prepare_dynamic_shared_mem(config.shared_mem_bytes)?;
+        ffi_call!(
+            cuLaunchKernel,
+            self.func,
+            config.grid.0.x,
+            config.grid.0.y,
+            config.grid.0.z,
+            config.block.0.x,
+            config.block.0.y,
+            config.block.0.z,
+            config.shared_mem_bytes,
+            null_mut(), // use default stream
+            args,
+            null_mut() // no extra
+        )
+    }
+
+    /// Launch this kernel onto `stream` without blocking the host thread.
+    ///
+    /// Submitting several kernels onto distinct streams before calling
+    /// [Stream::synchronize] lets the driver overlap their copies and compute
+    /// instead of serializing everything on the default stream.
+    pub unsafe fn launch_async(
+        &mut self,
+        stream: &Stream,
+        args: *mut *mut c_void,
+        config: &LaunchConfig,
+    ) -> Result<()> {
+        self.prepare_dynamic_shared_mem(config.shared_mem_bytes)?;
+        ffi_call!(
+            cuLaunchKernel,
+            self.func,
+            config.grid.0.x,
+            config.grid.0.y,
+            config.grid.0.z,
+            config.block.0.x,
+            config.block.0.y,
+            config.block.0.z,
+            config.shared_mem_bytes,
+            stream.as_ptr(),
+            args,
+            null_mut() // no extra
+        )
+    }
+
+    /// Raise the dynamic shared memory limit for this function if `dynamic_bytes`, together with
+    /// whatever static `ThreadBlockShared` memory is already compiled into it, exceeds the
+    /// device's default static-shared-memory allotment.
+    ///
+    /// The static contribution is read back from the compiled kernel itself via
+    /// `CU_FUNC_ATTRIBUTE_SHARED_SIZE_BYTES` rather than assumed to be zero, and the ceiling for
+    /// the opt-in is the *actual* device limit (`CU_DEVICE_ATTRIBUTE_MAX_SHARED_MEMORY_PER_BLOCK_OPTIN`),
+    /// not the hardcoded default every device starts out with.
+    ///
+    /// See also [cuFuncSetAttribute].
+    ///
+    /// [cuFuncSetAttribute]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__EXEC.html#group__CUDA__EXEC_1g42890fcadb14f8e65d1b36e9e6ed1d3d
+    fn prepare_dynamic_shared_mem(&mut self, dynamic_bytes: u32) -> Result<()> {
+        let static_bytes =
+            self.attribute(CUfunction_attribute_enum::CU_FUNC_ATTRIBUTE_SHARED_SIZE_BYTES)? as u32;
+        let total_bytes = static_bytes + dynamic_bytes;
+        if total_bytes > DEFAULT_MAX_DYNAMIC_SHARED_MEM_BYTES {
+            let max_optin = self.max_shared_mem_per_block_optin()?;
+            if total_bytes > max_optin {
+                return Err(AccelError::SharedMemoryLimitExceeded {
+                    requested: total_bytes,
+                    max: max_optin,
+                });
+            }
+            ffi_call!(
+                cuFuncSetAttribute,
+                self.func,
+                CUfunction_attribute_enum::CU_FUNC_ATTRIBUTE_MAX_DYNAMIC_SHARED_SIZE_BYTES,
+                dynamic_bytes as i32
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Query this function's device for its per-block shared memory limit when opted in to
+    /// exceeding the default static allotment.
+    ///
+    /// See also [cuDeviceGetAttribute].
+    ///
+    /// [cuDeviceGetAttribute]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__DEVICE.html#group__CUDA__DEVICE_1g9c3e1414f0ad901d3278a4d6645fc266
+    fn max_shared_mem_per_block_optin(&self) -> Result<u32> {
+        let _g = self.guard_context();
+        let device = ffi_new!(cuCtxGetDevice)?;
+        let limit = ffi_new!(
+            cuDeviceGetAttribute,
+            CUdevice_attribute_enum::CU_DEVICE_ATTRIBUTE_MAX_SHARED_MEMORY_PER_BLOCK_OPTIN,
+            device
+        )?;
+        Ok(limit as u32)
+    }
+
+    /// Query a single attribute of this kernel (max threads per block, register count, shared
+    /// memory usage, PTX/binary version, ...).
+    ///
+    /// See also [cuFuncGetAttribute].
+    ///
+    /// [cuFuncGetAttribute]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__EXEC.html#group__CUDA__EXEC_1g5e92a1b0d8d1b82cb00dcfb2de15961b
+    pub fn attribute(&self, attr: CUfunction_attribute) -> Result<i32> {
+        Ok(ffi_new!(cuFuncGetAttribute, attr, self.func)?)
+    }
+
+    /// Suggest a grid/block size that maximizes occupancy for this kernel.
+    ///
+    /// Returns the minimum grid size needed to achieve maximum occupancy together with the
+    /// largest block size that achieves it; given a total element count `n`, compute the actual
+    /// grid to launch as `grid = (n + block - 1) / block`.
+    ///
+    /// See also [cuOccupancyMaxPotentialBlockSize].
+    ///
+    /// [cuOccupancyMaxPotentialBlockSize]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__OCCUPANCY.html#group__CUDA__OCCUPANCY_1gf179c4ab78962a8468e41c3f57851f03
+    pub fn suggested_launch_config(
+        &mut self,
+        dynamic_smem_bytes: u32,
+        block_size_limit: u32,
+    ) -> Result<(Grid, Block)> {
+        let mut min_grid_size: i32 = 0;
+        let mut block_size: i32 = 0;
+        ffi_call!(
+            cuOccupancyMaxPotentialBlockSize,
+            &mut min_grid_size as *mut i32,
+            &mut block_size as *mut i32,
+            self.func,
+            None, // no dynamic shared memory sizing callback
+            dynamic_smem_bytes as usize,
+            block_size_limit as i32
+        )?;
+        Ok((Grid::x(min_grid_size as u32), Block::x(block_size as u32)))
+    }
+
+    /// Set the preferred on-chip memory split between L1 cache and shared memory.
+    ///
+    /// See also [cuFuncSetCacheConfig].
+    ///
+    /// [cuFuncSetCacheConfig]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__EXEC.html#group__CUDA__EXEC_1g40f6a636611559c3e2e609ed1ec45e9e
+    pub fn set_cache_config(&mut self, config: CUfunc_cache) -> Result<()> {
+        ffi_call!(cuFuncSetCacheConfig, self.func, config)
+    }
+
+    /// Set the preferred shared memory bank size for this kernel.
+    ///
+    /// See also [cuFuncSetSharedMemConfig].
+    ///
+    /// [cuFuncSetSharedMemConfig]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__EXEC.html#group__CUDA__EXEC_1g8a99d401d46a60407199e50af0b07798
+    pub fn set_shared_mem_config(&mut self, config: CUsharedconfig) -> Result<()> {
+        ffi_call!(cuFuncSetSharedMemConfig, self.func, config)
+    }
+}
+
+/// Default device limit on *static* shared memory per block (48 KiB). Requesting more dynamic
+/// shared memory than this via [LaunchConfig::shared_mem_bytes] requires raising
+/// `CU_FUNC_ATTRIBUTE_MAX_DYNAMIC_SHARED_SIZE_BYTES`, which [Function::launch]/[Function::launch_async]
+/// do automatically.
+const DEFAULT_MAX_DYNAMIC_SHARED_MEM_BYTES: u32 = 48 * 1024;
+
+/// Grid/block dimensions and dynamic shared memory for [Function::launch]/[Function::launch_async]
+///
+/// This intentionally has no `stream` field: [Function::launch] always uses the default stream
+/// and [Function::launch_async] already takes its `&Stream` as an explicit argument, so a
+/// `LaunchConfig` is shared between both call sites without one branch ignoring it.
+#[derive(Debug, Clone, Copy)]
+pub struct LaunchConfig {
+    pub grid: Grid,
+    pub block: Block,
+    /// Bytes of dynamic `extern __shared__` memory requested by the kernel, in addition to any
+    /// statically-sized `ThreadBlockShared` declared in the kernel itself.
+    pub shared_mem_bytes: u32,
+}
+
+impl LaunchConfig {
+    /// A launch with no dynamic shared memory.
+    pub fn new(grid: Grid, block: Block) -> Self {
+        LaunchConfig {
+            grid,
+            block,
+            shared_mem_bytes: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Grid(dim3);
+
+impl Grid {
+    pub fn x(x: u32) -> Self {
+        Grid(dim3 { x, y: 1, z: 1 })
+    }
+
+    pub fn xy(x: u32, y: u32) -> Self {
+        Grid(dim3 { x, y, z: 1 })
+    }
+
+    pub fn xyz(x: u32, y: u32, z: u32) -> Self {
+        Grid(dim3 { x, y, z })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Block(dim3);
+
+impl Block {
+    pub fn x(x: u32) -> Self {
+        Block(dim3 { x, y: 1, z: 1 })
+    }
+
+    pub fn xy(x: u32, y: u32) -> Self {
+        Block(dim3 { x, y, z: 1 })
+    }
+
+    pub fn xyz(x: u32, y: u32, z: u32) -> Self {
+        Block(dim3 { x, y, z })
+    }
+}
@@ -0,0 +1,105 @@
+//! CUDA [Stream] and [Event] for asynchronous kernel execution
+//!
+//! [Stream]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__STREAM.html
+//! [Event]:  https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__EVENT.html
+
+use crate::{device::*, error::*, ffi_call, ffi_new};
+use cuda::*;
+
+/// Owned handler for a non-default CUDA stream, tied to the [Context] it was created on
+///
+/// Kernels and copies submitted to the same stream execute in order, while work on distinct
+/// streams may run concurrently, allowing e.g. a host-to-device copy to overlap with a kernel
+/// launched on another stream. Use [Stream::synchronize] to block until all work submitted so
+/// far has completed.
+pub struct Stream<'ctx> {
+    ptr: CUstream,
+    context: &'ctx Context,
+}
+
+impl<'ctx> Drop for Stream<'ctx> {
+    fn drop(&mut self) {
+        if let Err(e) = ffi_call!(cuStreamDestroy_v2, self.ptr) {
+            log::error!("Failed to delete CUDA stream: {:?}", e);
+        }
+    }
+}
+
+impl<'ctx> Contexted for Stream<'ctx> {
+    fn get_context(&self) -> &Context {
+        &self.context
+    }
+}
+
+impl<'ctx> Stream<'ctx> {
+    /// Create a new stream on the given context.
+    ///
+    /// See also [cuStreamCreate].
+    ///
+    /// [cuStreamCreate]: https://docs.nvidia.com/cuda/cuda-driver-api/group__CUDA__STREAM.html#group__CUDA__STREAM_1ga581f0c5833e21ded8b5a56594e243f4
+    pub fn new(context: &'ctx Context, flags: CUstream_flags) -> Self {
+        let _g = context.guard_context();
+        let ptr = ffi_new!(cuStreamCreate, flags as u32).expect("Failed to create CUDA stream");
+        Stream { ptr, context }
+    }
+
+    pub(crate) fn as_ptr(&self) -> CUstream {
+        self.ptr
+    }
+
+    /// Block the host thread until all tasks submitted to this stream have completed.
+    pub fn synchronize(&self) -> Result<()> {
+        ffi_call!(cuStreamSynchronize, self.ptr)
+    }
+
+    /// Make all future work submitted to this stream wait until `event` has been recorded.
+    pub fn wait_event(&self, event: &Event) -> Result<()> {
+        ffi_call!(cuStreamWaitEvent, self.ptr, event.ptr, 0)
+    }
+}
+
+/// Owned handler for a CUDA event, used to time or synchronize work on a [Stream]
+pub struct Event<'ctx> {
+    ptr: CUevent,
+    context: &'ctx Context,
+}
+
+impl<'ctx> Drop for Event<'ctx> {
+    fn drop(&mut self) {
+        if let Err(e) = ffi_call!(cuEventDestroy_v2, self.ptr) {
+            log::error!("Failed to delete CUDA event: {:?}", e);
+        }
+    }
+}
+
+impl<'ctx> Contexted for Event<'ctx> {
+    fn get_context(&self) -> &Context {
+        &self.context
+    }
+}
+
+impl<'ctx> Event<'ctx> {
+    /// Create a new event on the given context.
+    pub fn new(context: &'ctx Context, flags: CUevent_flags) -> Self {
+        let _g = context.guard_context();
+        let ptr = ffi_new!(cuEventCreate, flags as u32).expect("Failed to create CUDA event");
+        Event { ptr, context }
+    }
+
+    /// Record this event into `stream`, marking the point other streams can wait on.
+    pub fn record(&mut self, stream: &Stream) -> Result<()> {
+        ffi_call!(cuEventRecord, self.ptr, stream.as_ptr())
+    }
+
+    /// Block the host thread until this event has been recorded.
+    pub fn synchronize(&self) -> Result<()> {
+        ffi_call!(cuEventSynchronize, self.ptr)
+    }
+
+    /// Elapsed time in milliseconds between two recorded events.
+    pub fn elapsed(start: &Event, stop: &Event) -> Result<f32> {
+        let mut ms: f32 = 0.0;
+        ffi_call!(cuEventElapsedTime, &mut ms as *mut _, start.ptr, stop.ptr)?;
+        Ok(ms)
+    }
+}